@@ -1,7 +1,7 @@
 #![deny(unsafe_code)]
 
 use serde::{Deserialize, Serialize};
-use wide::{CmpEq, CmpGt, f32x4, f32x8, u32x4, u32x8};
+use wide::{CmpEq, CmpGe, CmpGt, f32x4, f32x8, u32x4, u32x8};
 
 /// Options for the diff algorithm
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +12,35 @@ pub struct DiffOptions {
     pub aa_color: [u8; 3],
     pub diff_color: [u8; 3],
     pub diff_color_alt: Option<[u8; 3]>,
+    /// Background composited under semi-transparent pixels before comparison.
+    /// Defaults to white; set this to match the UI under test (e.g. a dark
+    /// theme) so transparent edges don't register as bogus diffs.
+    pub bg_color: [u8; 3],
+    /// How the alpha channel factors into the per-pixel color delta.
+    pub alpha_mode: AlphaMode,
+    /// Color-distance metric `threshold` is scaled against.
+    pub metric: Metric,
+    /// Neighbor connectivity used when grouping diff pixels into `DiffResult::regions`.
+    pub region_connectivity: Connectivity,
+    /// When set, `diff_images`/`diff_bytes` resample mismatched-size inputs to a
+    /// common size instead of returning an error.
+    pub resize: Option<ResizePolicy>,
+    /// Filter used by the resampler when `resize` is set.
+    pub resize_filter: ResizeFilter,
+    /// Restricts the comparison to the selected RGBA channels.
+    pub channels: ChannelMask,
+    /// When set, `DiffResult` carries one single-channel diff mask per selected channel.
+    pub per_channel_output: bool,
+    /// When set, both inputs are run through a separable blur before comparison,
+    /// a cheaper alternative to `include_aa` for suppressing font-hinting/GPU
+    /// anti-aliasing ripples.
+    pub prefilter: Option<BlurConfig>,
+    /// Rectangles, as `(x, y, width, height)`, to exclude from comparison: a
+    /// pixel inside any of these never counts toward `diff_count`, `regions`,
+    /// or `bounding_box`, however much it differs. Lets callers mask out
+    /// dynamic content (timestamps, ads) that would otherwise cause spurious
+    /// failures.
+    pub ignore_regions: Vec<(u32, u32, u32, u32)>,
 }
 
 impl Default for DiffOptions {
@@ -23,10 +52,146 @@ impl Default for DiffOptions {
             aa_color: [255, 255, 0],   // yellow
             diff_color: [255, 0, 255], // magenta
             diff_color_alt: None,
+            bg_color: [255, 255, 255], // white
+            alpha_mode: AlphaMode::OverBackground,
+            metric: Metric::Yiq,
+            region_connectivity: Connectivity::Eight,
+            resize: None,
+            resize_filter: ResizeFilter::Bilinear,
+            channels: ChannelMask::ALL,
+            per_channel_output: false,
+            prefilter: None,
+            ignore_regions: Vec::new(),
         }
     }
 }
 
+/// Separable blur applied to both inputs before `diff_rgba` when `DiffOptions::prefilter`
+/// is set.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BlurConfig {
+    /// Kernel radius in pixels; the 1-D kernel spans `2 * radius + 1` taps.
+    pub radius: u32,
+    pub kind: BlurKind,
+}
+
+/// Shape of the 1-D kernel used by `BlurConfig`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BlurKind {
+    /// Uniform weights over the kernel window.
+    Box,
+    /// `k[i] = exp(-(i-r)^2 / (2 * sigma^2))`, normalized to sum to 1.
+    Gaussian { sigma: f32 },
+}
+
+/// Bitmask selecting which RGBA channels participate in the comparison,
+/// borrowed from the `ChannelOptions` concept in Ruffle's `BitmapData`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelMask(u8);
+
+impl ChannelMask {
+    pub const R: ChannelMask = ChannelMask(0b0001);
+    pub const G: ChannelMask = ChannelMask(0b0010);
+    pub const B: ChannelMask = ChannelMask(0b0100);
+    pub const A: ChannelMask = ChannelMask(0b1000);
+    pub const ALL: ChannelMask = ChannelMask(0b1111);
+
+    pub fn contains(self, other: ChannelMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for ChannelMask {
+    type Output = ChannelMask;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        ChannelMask(self.0 | rhs.0)
+    }
+}
+
+/// How to reconcile a size mismatch between the two inputs before diffing.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ResizePolicy {
+    /// Resample both images up to the larger of the two sizes.
+    MatchLarger,
+    /// Resample both images down to the smaller of the two sizes.
+    MatchSmaller,
+    /// Resample both images to an explicit size.
+    To { width: u32, height: u32 },
+}
+
+/// Resampling kernel used by the separable resizer.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ResizeFilter {
+    Box,
+    Bilinear,
+    Lanczos,
+}
+
+/// Controls how the alpha channel is folded into the perceptual color delta.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AlphaMode {
+    /// Composite both pixels over `bg_color` before comparing (today's default behavior).
+    OverBackground,
+    /// Multiply each RGB channel by its own alpha before the YIQ transform, so a
+    /// color change under low alpha contributes proportionally less.
+    PremultipliedColor,
+    /// Compare colors composited over the background like `OverBackground`, but also
+    /// add the squared difference of the two alpha values so pure opacity changes
+    /// register as diffs even when the RGB channels are identical.
+    AlphaWeighted,
+    /// Binarize both pixels as visible/invisible by comparing alpha against
+    /// `threshold`, flag a diff whenever that visibility flips, and otherwise
+    /// compare color only among mutually-visible pixels.
+    AlphaTest { threshold: u8 },
+}
+
+/// Per-pixel color-distance metric used to decide whether two pixels differ.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Metric {
+    /// Perceptual YIQ color distance (as used by pixelmatch/odiff), so
+    /// `DiffOptions::threshold` maps to perceived difference rather than raw
+    /// RGB deltas. The default.
+    Yiq,
+    /// Naive sum of squared raw RGB channel deltas, with no perceptual
+    /// weighting. Kept for callers that relied on the pre-YIQ behavior.
+    Rgb,
+}
+
+/// Weight applied to the squared alpha delta in `AlphaMode::AlphaWeighted`,
+/// normalized so a full-opacity swing (255²) contributes about as much as a
+/// full YIQ color flip (`YIQ_MAX_DELTA`), keeping opacity and color changes
+/// comparable in magnitude instead of one dominating the other.
+const ALPHA_DIFF_WEIGHT: f32 = YIQ_MAX_DELTA / (255.0 * 255.0);
+
+/// A single typed reason two images differ, as opposed to a bare pixel count.
+/// Modeled after havocompare's `Difference::detail`, so downstream tooling can
+/// render *why* two images differ rather than only *how much*.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DiffDetail {
+    /// The two source images had different dimensions before any `resize`
+    /// policy in `DiffOptions` was applied. Only emitted by `diff_images`/
+    /// `diff_bytes`, which see the pre-reconciliation sizes; `diff_rgba` takes
+    /// already-matched buffers and never produces this variant.
+    DimensionMismatch {
+        nominal_width: u32,
+        nominal_height: u32,
+        actual_width: u32,
+        actual_height: u32,
+    },
+    /// A selected channel accumulated at least one differing pixel. Only
+    /// populated when `DiffOptions::per_channel_output` is set, since that's
+    /// what computes the per-channel masks this is derived from.
+    ChannelDifference { channel: ChannelMask, diff_count: u32 },
+    /// One connected component of differing pixels, mirroring an entry of
+    /// `DiffResult::regions` in `(x, y, width, height)` form.
+    Region {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+}
+
 /// Result of the diff operation
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DiffResult {
@@ -34,6 +199,26 @@ pub struct DiffResult {
     pub output: Vec<u8>,
     pub width: u32,
     pub height: u32,
+    /// (min_x, min_y, max_x, max_y) over every pixel that counted toward `diff_count`.
+    pub bounding_box: Option<(u32, u32, u32, u32)>,
+    /// One bounding rectangle per connected component of diff pixels, via
+    /// connected-component labeling over the diff mask.
+    pub regions: Vec<(u32, u32, u32, u32)>,
+    /// Populated when `DiffOptions::per_channel_output` is set: one single-channel
+    /// absolute-difference mask (one byte per pixel) per channel selected in
+    /// `DiffOptions::channels`.
+    pub per_channel_diffs: Vec<(ChannelMask, Vec<u8>)>,
+    /// `regions` and `per_channel_diffs` restated as typed `DiffDetail`s, plus
+    /// (from `diff_images`/`diff_bytes` only) a `DimensionMismatch` when the
+    /// sources needed resampling to compare at all.
+    pub details: Vec<DiffDetail>,
+}
+
+/// Neighbor connectivity used when grouping diff pixels into regions.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Connectivity {
+    Four,
+    Eight,
 }
 
 // Pre-computed YIQ coefficients as constants
@@ -53,6 +238,13 @@ const YIQ_Y_WEIGHT: f32 = 0.5053;
 const YIQ_I_WEIGHT: f32 = 0.299;
 const YIQ_Q_WEIGHT: f32 = 0.1957;
 
+/// Maximum possible squared YIQ distance between two 8-bit-channel colors,
+/// used to scale `DiffOptions::threshold` into an absolute delta for `Metric::Yiq`.
+const YIQ_MAX_DELTA: f32 = 35215.0;
+/// Maximum possible squared raw-RGB distance between two 8-bit-channel colors
+/// (`3 * 255^2`), the `Metric::Rgb` equivalent of `YIQ_MAX_DELTA`.
+const RGB_MAX_DELTA: f32 = 195_075.0;
+
 // SIMD constants - these will be initialized at runtime
 #[inline(always)]
 fn get_simd_constants() -> (
@@ -85,6 +277,41 @@ fn get_simd_constants() -> (
     )
 }
 
+/// Whether `(x, y)` falls inside any of `DiffOptions::ignore_regions`. Checked
+/// per-pixel rather than vectorized, mirroring the scalar anti-aliasing check
+/// already threaded through the same loop in `diff_rgba`.
+#[inline]
+fn pixel_is_ignored(x: u32, y: u32, ignore_regions: &[(u32, u32, u32, u32)]) -> bool {
+    ignore_regions.iter().any(|&(rx, ry, rw, rh)| {
+        x >= rx && x < rx.saturating_add(rw) && y >= ry && y < ry.saturating_add(rh)
+    })
+}
+
+/// Background channels splatted into SIMD lanes so the compositing path
+/// stays branch-free regardless of which `bg_color` the caller picked.
+#[inline(always)]
+fn get_simd_bg_constants_x8(bg_color: [u8; 3]) -> (f32x8, f32x8, f32x8, f32x8) {
+    let bg_r = bg_color[0] as f32;
+    let bg_g = bg_color[1] as f32;
+    let bg_b = bg_color[2] as f32;
+    let bg_luma = bg_r * Y_R + bg_g * Y_G + bg_b * Y_B;
+    (
+        f32x8::splat(bg_r),
+        f32x8::splat(bg_g),
+        f32x8::splat(bg_b),
+        f32x8::splat(bg_luma),
+    )
+}
+
+#[inline(always)]
+fn get_simd_bg_constants_x4(bg_color: [u8; 3]) -> (f32x4, f32x4, f32x4) {
+    (
+        f32x4::splat(bg_color[0] as f32),
+        f32x4::splat(bg_color[1] as f32),
+        f32x4::splat(bg_color[2] as f32),
+    )
+}
+
 /// Main diff function for RGBA images with SIMD optimization
 pub fn diff_rgba(
     img1: &[u8],
@@ -98,12 +325,40 @@ pub fn diff_rgba(
     let h = height as usize;
     let total_pixels = w * h;
 
+    // Blur both inputs identically before comparing, if configured; this
+    // collapses the single-pixel ripples font hinting/GPU AA produce. Only
+    // the comparison below reads from the blurred copies — the rendered
+    // output image and `per_channel_diffs` still reflect the true source
+    // pixels, since blurring is a threshold-decision aid, not a rewrite of
+    // what the user is diffing.
+    let blurred1;
+    let blurred2;
+    let (cmp1, cmp2): (&[u8], &[u8]) = if let Some(config) = opts.prefilter {
+        blurred1 = blur_rgba(img1, w, h, config);
+        blurred2 = blur_rgba(img2, w, h, config);
+        (&blurred1, &blurred2)
+    } else {
+        (img1, img2)
+    };
+
     // Pre-allocate output buffer
     let mut output = vec![0u8; total_pixels * 4];
     let mut diff_count = 0u32;
 
+    // Tracks which pixels counted toward `diff_count`, used afterwards to
+    // extract the bounding box and connected-component regions.
+    let mut region_mask = vec![false; total_pixels];
+    let mut bbox_min_x = u32::MAX;
+    let mut bbox_min_y = u32::MAX;
+    let mut bbox_max_x = 0u32;
+    let mut bbox_max_y = 0u32;
+
     // Pre-compute threshold
-    let max_delta = 35215.0 * (opts.threshold * opts.threshold);
+    let metric_max_delta = match opts.metric {
+        Metric::Yiq => YIQ_MAX_DELTA,
+        Metric::Rgb => RGB_MAX_DELTA,
+    };
+    let max_delta = metric_max_delta * (opts.threshold * opts.threshold);
     let simd_max_delta = f32x8::splat(max_delta);
 
     // Pre-compute alpha blend factor
@@ -126,6 +381,9 @@ pub fn diff_rgba(
         simd_yiq_q_weight,
     ) = get_simd_constants();
 
+    let (simd_bg_r, simd_bg_g, simd_bg_b, simd_bg_luma) = get_simd_bg_constants_x8(opts.bg_color);
+    let bg_color = opts.bg_color;
+
     // Process in SIMD-friendly chunks
     const SIMD_WIDTH: usize = 8; // Process 8 pixels at once
 
@@ -138,15 +396,21 @@ pub fn diff_rgba(
             let base_pos = row_offset + x * 4;
 
             // Load 8 pixels worth of data (32 bytes each image)
-            let pixels1 = load_8_pixels_u32(img1, base_pos);
-            let pixels2 = load_8_pixels_u32(img2, base_pos);
+            let pixels1 = load_8_pixels_u32(cmp1, base_pos);
+            let pixels2 = load_8_pixels_u32(cmp2, base_pos);
 
             // Check for exact matches first
             let exact_matches = pixels1.cmp_eq(pixels2);
 
             if exact_matches.all() {
                 // All pixels match exactly, draw gray pixels
-                draw_8_gray_pixels_fast(img1, base_pos, simd_alpha_blend, &mut output);
+                draw_8_gray_pixels_fast(
+                    img1,
+                    base_pos,
+                    simd_alpha_blend,
+                    simd_bg_luma,
+                    &mut output,
+                );
             } else {
                 // Calculate color deltas for all 8 pixels
                 let deltas = calculate_8_pixel_color_deltas_fast(
@@ -164,6 +428,12 @@ pub fn diff_rgba(
                     &simd_yiq_y_weight,
                     &simd_yiq_i_weight,
                     &simd_yiq_q_weight,
+                    &simd_bg_r,
+                    &simd_bg_g,
+                    &simd_bg_b,
+                    opts.alpha_mode,
+                    opts.channels,
+                    opts.metric,
                 );
 
                 // Compare with threshold
@@ -173,29 +443,36 @@ pub fn diff_rgba(
                 for i in 0..SIMD_WIDTH {
                     let pixel_pos = base_pos + i * 4;
                     let is_exact_match = exact_matches.as_array_ref()[i] != 0;
-                    let is_diff = diff_mask.as_array_ref()[i] != 0.0;
+                    let is_diff = diff_mask.as_array_ref()[i] != 0.0
+                        && !pixel_is_ignored((x + i) as u32, y as u32, &opts.ignore_regions);
 
                     if is_exact_match {
-                        draw_gray_pixel_fast(img1, pixel_pos, alpha_blend, &mut output);
+                        draw_gray_pixel_fast(img1, pixel_pos, alpha_blend, bg_color, &mut output);
                     } else if is_diff {
                         // Check if this is anti-aliasing
                         if opts.include_aa
                             && is_pixel_antialiased_optimized(
-                                img1,
-                                img2,
+                                cmp1,
+                                cmp2,
                                 (x + i) as i32,
                                 y as i32,
                                 w as i32,
                                 h as i32,
+                                bg_color,
                             )
                         {
                             write_color(&mut output, pixel_pos, &opts.aa_color);
                         } else {
                             write_color(&mut output, pixel_pos, &opts.diff_color);
                             diff_count += 1;
+                            region_mask[y * w + x + i] = true;
+                            bbox_min_x = bbox_min_x.min((x + i) as u32);
+                            bbox_min_y = bbox_min_y.min(y as u32);
+                            bbox_max_x = bbox_max_x.max((x + i) as u32);
+                            bbox_max_y = bbox_max_y.max(y as u32);
                         }
                     } else {
-                        draw_gray_pixel_fast(img1, pixel_pos, alpha_blend, &mut output);
+                        draw_gray_pixel_fast(img1, pixel_pos, alpha_blend, bg_color, &mut output);
                     }
                 }
             }
@@ -208,42 +485,253 @@ pub fn diff_rgba(
             let pos = row_offset + x * 4;
 
             // Load pixels once
-            let pixel1 = load_pixel_u32(img1, pos);
-            let pixel2 = load_pixel_u32(img2, pos);
+            let pixel1 = load_pixel_u32(cmp1, pos);
+            let pixel2 = load_pixel_u32(cmp2, pos);
 
             if pixel1 == pixel2 {
-                draw_gray_pixel_fast(img1, pos, alpha_blend, &mut output);
+                draw_gray_pixel_fast(img1, pos, alpha_blend, bg_color, &mut output);
             } else {
-                let delta = calculate_pixel_color_delta_fast(pixel1, pixel2);
+                let delta = calculate_pixel_color_delta_fast(
+                    pixel1,
+                    pixel2,
+                    bg_color,
+                    opts.alpha_mode,
+                    opts.channels,
+                    opts.metric,
+                );
 
-                if delta > max_delta {
+                if delta > max_delta && !pixel_is_ignored(x as u32, y as u32, &opts.ignore_regions) {
                     // Check if this is anti-aliasing
                     if opts.include_aa
                         && is_pixel_antialiased_optimized(
-                            img1, img2, x as i32, y as i32, w as i32, h as i32,
+                            cmp1, cmp2, x as i32, y as i32, w as i32, h as i32, bg_color,
                         )
                     {
                         write_color(&mut output, pos, &opts.aa_color);
                     } else {
                         write_color(&mut output, pos, &opts.diff_color);
                         diff_count += 1;
+                        region_mask[y * w + x] = true;
+                        bbox_min_x = bbox_min_x.min(x as u32);
+                        bbox_min_y = bbox_min_y.min(y as u32);
+                        bbox_max_x = bbox_max_x.max(x as u32);
+                        bbox_max_y = bbox_max_y.max(y as u32);
                     }
                 } else {
-                    draw_gray_pixel_fast(img1, pos, alpha_blend, &mut output);
+                    draw_gray_pixel_fast(img1, pos, alpha_blend, bg_color, &mut output);
                 }
             }
             x += 1;
         }
     }
 
+    let bounding_box = if diff_count > 0 {
+        Some((bbox_min_x, bbox_min_y, bbox_max_x, bbox_max_y))
+    } else {
+        None
+    };
+    let regions = find_diff_regions(&region_mask, w, h, opts.region_connectivity);
+    let per_channel_diffs = if opts.per_channel_output {
+        compute_per_channel_diffs(img1, img2, total_pixels, opts.channels)
+    } else {
+        Vec::new()
+    };
+
+    let mut details: Vec<DiffDetail> = regions
+        .iter()
+        .map(|&(min_x, min_y, max_x, max_y)| DiffDetail::Region {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+        })
+        .collect();
+    details.extend(per_channel_diffs.iter().filter_map(|(channel, plane)| {
+        let diff_count = plane.iter().filter(|&&d| d > 0).count() as u32;
+        (diff_count > 0).then_some(DiffDetail::ChannelDifference {
+            channel: *channel,
+            diff_count,
+        })
+    }));
+
     DiffResult {
         diff_count,
         output,
         width,
         height,
+        bounding_box,
+        regions,
+        per_channel_diffs,
+        details,
     }
 }
 
+/// Separate linear pass producing a raw absolute-difference mask per selected
+/// channel, independent of `bg_color`/`alpha_mode` and of whether a pixel was
+/// actually flagged as different, so an isolated channel (e.g. alpha) can be
+/// inspected on its own.
+fn compute_per_channel_diffs(
+    img1: &[u8],
+    img2: &[u8],
+    total_pixels: usize,
+    channels: ChannelMask,
+) -> Vec<(ChannelMask, Vec<u8>)> {
+    const PLANES: [(ChannelMask, usize); 4] = [
+        (ChannelMask::R, 0),
+        (ChannelMask::G, 1),
+        (ChannelMask::B, 2),
+        (ChannelMask::A, 3),
+    ];
+
+    let mut selected: Vec<(ChannelMask, usize, Vec<u8>)> = PLANES
+        .into_iter()
+        .filter(|(mask, _)| channels.contains(*mask))
+        .map(|(mask, offset)| (mask, offset, vec![0u8; total_pixels]))
+        .collect();
+
+    // Single pass over both buffers filling every selected plane at once,
+    // rather than re-reading the images once per channel.
+    for i in 0..total_pixels {
+        for (_, offset, plane) in &mut selected {
+            plane[i] = img1[i * 4 + *offset].abs_diff(img2[i * 4 + *offset]);
+        }
+    }
+
+    selected
+        .into_iter()
+        .map(|(mask, _, plane)| (mask, plane))
+        .collect()
+}
+
+/// Union-find over diff-region labels with path compression.
+struct RegionLabels {
+    parent: Vec<u32>,
+}
+
+impl RegionLabels {
+    fn new() -> Self {
+        Self { parent: Vec::new() }
+    }
+
+    fn make_label(&mut self) -> u32 {
+        let label = self.parent.len() as u32;
+        self.parent.push(label);
+        label
+    }
+
+    fn find(&mut self, label: u32) -> u32 {
+        let mut root = label;
+        while self.parent[root as usize] != root {
+            root = self.parent[root as usize];
+        }
+        let mut cur = label;
+        while self.parent[cur as usize] != root {
+            let next = self.parent[cur as usize];
+            self.parent[cur as usize] = root;
+            cur = next;
+        }
+        root
+    }
+
+    fn union(&mut self, a: u32, b: u32) {
+        let root_a = self.find(a);
+        let root_b = self.find(b);
+        if root_a != root_b {
+            let (keep, merge) = if root_a < root_b {
+                (root_a, root_b)
+            } else {
+                (root_b, root_a)
+            };
+            self.parent[merge as usize] = keep;
+        }
+    }
+}
+
+/// Two-pass connected-component labeling over a boolean diff-pixel grid,
+/// returning one `(min_x, min_y, max_x, max_y)` bounding rectangle per blob.
+fn find_diff_regions(
+    diff_mask: &[bool],
+    w: usize,
+    h: usize,
+    connectivity: Connectivity,
+) -> Vec<(u32, u32, u32, u32)> {
+    let mut labels = vec![0u32; w * h];
+    let mut uf = RegionLabels::new();
+
+    // Pass 1: assign provisional labels, recording equivalences between the
+    // label above and the label(s) to the left/diagonal.
+    for y in 0..h {
+        for x in 0..w {
+            if !diff_mask[y * w + x] {
+                continue;
+            }
+
+            let mut neighbor_labels = [None; 4];
+            let mut n = 0;
+            if x > 0 && diff_mask[y * w + x - 1] {
+                neighbor_labels[n] = Some(labels[y * w + x - 1]);
+                n += 1;
+            }
+            if y > 0 {
+                if diff_mask[(y - 1) * w + x] {
+                    neighbor_labels[n] = Some(labels[(y - 1) * w + x]);
+                    n += 1;
+                }
+                if connectivity == Connectivity::Eight {
+                    if x > 0 && diff_mask[(y - 1) * w + x - 1] {
+                        neighbor_labels[n] = Some(labels[(y - 1) * w + x - 1]);
+                        n += 1;
+                    }
+                    if x + 1 < w && diff_mask[(y - 1) * w + x + 1] {
+                        neighbor_labels[n] = Some(labels[(y - 1) * w + x + 1]);
+                        n += 1;
+                    }
+                }
+            }
+
+            let found: Vec<u32> = neighbor_labels[..n].iter().filter_map(|l| *l).collect();
+            if found.is_empty() {
+                labels[y * w + x] = uf.make_label();
+            } else {
+                let min_label = *found.iter().min().unwrap();
+                labels[y * w + x] = min_label;
+                for &label in &found {
+                    uf.union(min_label, label);
+                }
+            }
+        }
+    }
+
+    // Pass 2: flatten labels to roots and accumulate a bounding rect per root.
+    let mut rects: std::collections::HashMap<u32, (u32, u32, u32, u32)> =
+        std::collections::HashMap::new();
+    for y in 0..h {
+        for x in 0..w {
+            if !diff_mask[y * w + x] {
+                continue;
+            }
+            let root = uf.find(labels[y * w + x]);
+            let (x, y) = (x as u32, y as u32);
+            rects
+                .entry(root)
+                .and_modify(|r| {
+                    r.0 = r.0.min(x);
+                    r.1 = r.1.min(y);
+                    r.2 = r.2.max(x);
+                    r.3 = r.3.max(y);
+                })
+                .or_insert((x, y, x, y));
+        }
+    }
+
+    // HashMap iteration order is randomized per-process, which would make
+    // `DiffResult::regions`/`details` (and the JSON built from them) flaky
+    // across runs; sort into a deterministic order instead.
+    let mut regions: Vec<(u32, u32, u32, u32)> = rects.into_values().collect();
+    regions.sort_by_key(|&(min_x, min_y, _, _)| (min_y, min_x));
+    regions
+}
+
 /// Load 8 consecutive pixels as u32x8
 #[inline(always)]
 fn load_8_pixels_u32(img: &[u8], base_pos: usize) -> u32x8 {
@@ -272,9 +760,16 @@ fn calculate_8_pixel_color_deltas_fast(
     simd_yiq_y_weight: &f32x8,
     simd_yiq_i_weight: &f32x8,
     simd_yiq_q_weight: &f32x8,
+    simd_bg_r: &f32x8,
+    simd_bg_g: &f32x8,
+    simd_bg_b: &f32x8,
+    alpha_mode: AlphaMode,
+    channels: ChannelMask,
+    metric: Metric,
 ) -> f32x8 {
     let simd_255 = f32x8::splat(255.0);
     let simd_zero = f32x8::splat(0.0);
+    let (bg_r, bg_g, bg_b) = (*simd_bg_r, *simd_bg_g, *simd_bg_b);
 
     // Extract RGBA components for all 8 pixels
     let mask_r = u32x8::splat(0xFF);
@@ -300,7 +795,7 @@ fn calculate_8_pixel_color_deltas_fast(
     let b_a_u32: u32x8 = (pixels_b & mask_a) >> 24;
     let b_a = f32x8::new(b_a_u32.as_array_ref().map(|x| x as f32));
 
-    // Alpha blending with white background for all pixels
+    // Alpha blending against the configured background for all pixels
     let alpha_a = a_a / simd_255;
     let alpha_b = b_a / simd_255;
 
@@ -310,50 +805,95 @@ fn calculate_8_pixel_color_deltas_fast(
     let transparent_b = b_a.cmp_eq(simd_zero);
     let opaque_b = b_a.cmp_eq(simd_255);
 
-    // Blend colors
-    let r1 = transparent_a.blend(
-        simd_255,
-        opaque_a.blend(a_r, simd_255 + (a_r - simd_255) * alpha_a),
+    // Blend colors: bg + (src - bg) * alpha
+    let (r1, g1, b1) = (
+        transparent_a.blend(bg_r, opaque_a.blend(a_r, bg_r + (a_r - bg_r) * alpha_a)),
+        transparent_a.blend(bg_g, opaque_a.blend(a_g, bg_g + (a_g - bg_g) * alpha_a)),
+        transparent_a.blend(bg_b, opaque_a.blend(a_b, bg_b + (a_b - bg_b) * alpha_a)),
     );
-    let g1 = transparent_a.blend(
-        simd_255,
-        opaque_a.blend(a_g, simd_255 + (a_g - simd_255) * alpha_a),
-    );
-    let b1 = transparent_a.blend(
-        simd_255,
-        opaque_a.blend(a_b, simd_255 + (a_b - simd_255) * alpha_a),
+    let (r2, g2, b2) = (
+        transparent_b.blend(bg_r, opaque_b.blend(b_r, bg_r + (b_r - bg_r) * alpha_b)),
+        transparent_b.blend(bg_g, opaque_b.blend(b_g, bg_g + (b_g - bg_g) * alpha_b)),
+        transparent_b.blend(bg_b, opaque_b.blend(b_b, bg_b + (b_b - bg_b) * alpha_b)),
     );
 
-    let r2 = transparent_b.blend(
-        simd_255,
-        opaque_b.blend(b_r, simd_255 + (b_r - simd_255) * alpha_b),
-    );
-    let g2 = transparent_b.blend(
-        simd_255,
-        opaque_b.blend(b_g, simd_255 + (b_g - simd_255) * alpha_b),
-    );
-    let b2 = transparent_b.blend(
-        simd_255,
-        opaque_b.blend(b_b, simd_255 + (b_b - simd_255) * alpha_b),
-    );
+    let color_delta = |r1: f32x8, g1: f32x8, b1: f32x8, r2: f32x8, g2: f32x8, b2: f32x8| -> f32x8 {
+        // Masked-off channels are forced equal so they don't contribute to the
+        // delta below.
+        let r2 = if channels.contains(ChannelMask::R) { r2 } else { r1 };
+        let g2 = if channels.contains(ChannelMask::G) { g2 } else { g1 };
+        let b2 = if channels.contains(ChannelMask::B) { b2 } else { b1 };
+
+        match metric {
+            Metric::Yiq => {
+                let y_diff = (r1 * *simd_y_r + g1 * *simd_y_g + b1 * *simd_y_b)
+                    - (r2 * *simd_y_r + g2 * *simd_y_g + b2 * *simd_y_b);
+                let i_diff = (r1 * *simd_i_r - g1 * *simd_i_g - b1 * *simd_i_b)
+                    - (r2 * *simd_i_r - g2 * *simd_i_g - b2 * *simd_i_b);
+                let q_diff = (r1 * *simd_q_r - g1 * *simd_q_g + b1 * *simd_q_b)
+                    - (r2 * *simd_q_r - g2 * *simd_q_g + b2 * *simd_q_b);
+
+                *simd_yiq_y_weight * y_diff * y_diff
+                    + *simd_yiq_i_weight * i_diff * i_diff
+                    + *simd_yiq_q_weight * q_diff * q_diff
+            }
+            Metric::Rgb => {
+                let r_diff = r1 - r2;
+                let g_diff = g1 - g2;
+                let b_diff = b1 - b2;
+                r_diff * r_diff + g_diff * g_diff + b_diff * b_diff
+            }
+        }
+    };
 
-    // Calculate YIQ differences
-    let y_diff = (r1 * *simd_y_r + g1 * *simd_y_g + b1 * *simd_y_b)
-        - (r2 * *simd_y_r + g2 * *simd_y_g + b2 * *simd_y_b);
-    let i_diff = (r1 * *simd_i_r - g1 * *simd_i_g - b1 * *simd_i_b)
-        - (r2 * *simd_i_r - g2 * *simd_i_g - b2 * *simd_i_b);
-    let q_diff = (r1 * *simd_q_r - g1 * *simd_q_g + b1 * *simd_q_b)
-        - (r2 * *simd_q_r - g2 * *simd_q_g + b2 * *simd_q_b);
+    match alpha_mode {
+        AlphaMode::OverBackground => color_delta(r1, g1, b1, r2, g2, b2),
+        AlphaMode::PremultipliedColor => {
+            color_delta(a_r * alpha_a, a_g * alpha_a, a_b * alpha_a, b_r * alpha_b, b_g * alpha_b, b_b * alpha_b)
+        }
+        AlphaMode::AlphaWeighted => {
+            let base = color_delta(r1, g1, b1, r2, g2, b2);
+            if channels.contains(ChannelMask::A) {
+                let alpha_diff = a_a - b_a;
+                base + f32x8::splat(ALPHA_DIFF_WEIGHT) * alpha_diff * alpha_diff
+            } else {
+                base
+            }
+        }
+        AlphaMode::AlphaTest { threshold } => {
+            // Among mutually-visible pixels, compare raw (un-composited) color.
+            let raw_color_delta = color_delta(a_r, a_g, a_b, b_r, b_g, b_b);
 
-    // Final weighted sum
-    *simd_yiq_y_weight * y_diff * y_diff
-        + *simd_yiq_i_weight * i_diff * i_diff
-        + *simd_yiq_q_weight * q_diff * q_diff
+            if !channels.contains(ChannelMask::A) {
+                raw_color_delta
+            } else {
+                let simd_threshold = f32x8::splat(threshold as f32);
+                let visible_a = a_a.cmp_ge(simd_threshold);
+                let visible_b = b_a.cmp_ge(simd_threshold);
+                // `cmp_eq` is an ordered float compare: two all-ones (true) mask
+                // lanes are NaN bit patterns, and NaN == NaN is false, so it
+                // can't be used to detect "both visible". XOR the masks
+                // instead — all-ones only when exactly one side is visible.
+                let visibility_flipped = visible_a ^ visible_b;
+                let both_visible = visible_a & visible_b;
+                let huge_delta = f32x8::splat(f32::MAX);
+                let no_delta = f32x8::splat(0.0);
+
+                visibility_flipped.blend(huge_delta, both_visible.blend(raw_color_delta, no_delta))
+            }
+        }
+    }
 }
 
 /// Draw 8 gray pixels using SIMD
 #[inline]
-fn draw_8_gray_pixels_fast(img: &[u8], base_pos: usize, alpha_blend: f32x8, out: &mut [u8]) {
+fn draw_8_gray_pixels_fast(
+    img: &[u8],
+    base_pos: usize,
+    alpha_blend: f32x8,
+    bg_luma: f32x8,
+    out: &mut [u8],
+) {
     let simd_255 = f32x8::splat(255.0);
     let simd_zero = f32x8::splat(0.0);
     let simd_y_r = f32x8::splat(Y_R);
@@ -384,7 +924,7 @@ fn draw_8_gray_pixels_fast(img: &[u8], base_pos: usize, alpha_blend: f32x8, out:
 
     // Apply alpha blending
     let alpha_norm = a_simd / simd_255;
-    let val_simd = (simd_255 + (y_simd - simd_255) * alpha_blend * alpha_norm)
+    let val_simd = (bg_luma + (y_simd - bg_luma) * alpha_blend * alpha_norm)
         .max(simd_zero)
         .min(simd_255);
 
@@ -406,9 +946,50 @@ fn load_pixel_u32(img: &[u8], pos: usize) -> u32 {
     u32::from_ne_bytes([img[pos], img[pos + 1], img[pos + 2], img[pos + 3]])
 }
 
+#[inline(always)]
+fn color_delta_scalar(
+    r1: f32,
+    g1: f32,
+    b1: f32,
+    r2: f32,
+    g2: f32,
+    b2: f32,
+    channels: ChannelMask,
+    metric: Metric,
+) -> f32 {
+    // Masked-off channels are forced equal so they don't contribute to the
+    // delta below.
+    let r2 = if channels.contains(ChannelMask::R) { r2 } else { r1 };
+    let g2 = if channels.contains(ChannelMask::G) { g2 } else { g1 };
+    let b2 = if channels.contains(ChannelMask::B) { b2 } else { b1 };
+
+    match metric {
+        Metric::Yiq => {
+            let y_diff = (r1 * Y_R + g1 * Y_G + b1 * Y_B) - (r2 * Y_R + g2 * Y_G + b2 * Y_B);
+            let i_diff = (r1 * I_R - g1 * I_G - b1 * I_B) - (r2 * I_R - g2 * I_G - b2 * I_B);
+            let q_diff = (r1 * Q_R - g1 * Q_G + b1 * Q_B) - (r2 * Q_R - g2 * Q_G + b2 * Q_B);
+
+            YIQ_Y_WEIGHT * y_diff * y_diff + YIQ_I_WEIGHT * i_diff * i_diff + YIQ_Q_WEIGHT * q_diff * q_diff
+        }
+        Metric::Rgb => {
+            let r_diff = r1 - r2;
+            let g_diff = g1 - g2;
+            let b_diff = b1 - b2;
+            r_diff * r_diff + g_diff * g_diff + b_diff * b_diff
+        }
+    }
+}
+
 /// Optimized pixel color delta calculation (unchanged for fallback)
 #[inline(always)]
-fn calculate_pixel_color_delta_fast(pixel_a: u32, pixel_b: u32) -> f32 {
+fn calculate_pixel_color_delta_fast(
+    pixel_a: u32,
+    pixel_b: u32,
+    bg_color: [u8; 3],
+    alpha_mode: AlphaMode,
+    channels: ChannelMask,
+    metric: Metric,
+) -> f32 {
     // Extract components directly
     let a_a = ((pixel_a >> 24) & 0xFF) as f32;
     let a_b = ((pixel_a >> 16) & 0xFF) as f32;
@@ -420,44 +1001,86 @@ fn calculate_pixel_color_delta_fast(pixel_a: u32, pixel_b: u32) -> f32 {
     let b_g = ((pixel_b >> 8) & 0xFF) as f32;
     let b_r = (pixel_b & 0xFF) as f32;
 
-    // Blend with white background inline
+    let (bg_r, bg_g, bg_b) = (
+        bg_color[0] as f32,
+        bg_color[1] as f32,
+        bg_color[2] as f32,
+    );
+
+    // Blend with the configured background inline
     let (r1, g1, b1) = if a_a == 0.0 {
-        (255.0, 255.0, 255.0)
+        (bg_r, bg_g, bg_b)
     } else if a_a == 255.0 {
         (a_r, a_g, a_b)
     } else {
         let alpha = a_a / 255.0;
         (
-            255.0 + (a_r - 255.0) * alpha,
-            255.0 + (a_g - 255.0) * alpha,
-            255.0 + (a_b - 255.0) * alpha,
+            bg_r + (a_r - bg_r) * alpha,
+            bg_g + (a_g - bg_g) * alpha,
+            bg_b + (a_b - bg_b) * alpha,
         )
     };
 
     let (r2, g2, b2) = if b_a == 0.0 {
-        (255.0, 255.0, 255.0)
+        (bg_r, bg_g, bg_b)
     } else if b_a == 255.0 {
         (b_r, b_g, b_b)
     } else {
         let alpha = b_a / 255.0;
         (
-            255.0 + (b_r - 255.0) * alpha,
-            255.0 + (b_g - 255.0) * alpha,
-            255.0 + (b_b - 255.0) * alpha,
+            bg_r + (b_r - bg_r) * alpha,
+            bg_g + (b_g - bg_g) * alpha,
+            bg_b + (b_b - bg_b) * alpha,
         )
     };
 
-    // Calculate YIQ differences inline
-    let y_diff = (r1 * Y_R + g1 * Y_G + b1 * Y_B) - (r2 * Y_R + g2 * Y_G + b2 * Y_B);
-    let i_diff = (r1 * I_R - g1 * I_G - b1 * I_B) - (r2 * I_R - g2 * I_G - b2 * I_B);
-    let q_diff = (r1 * Q_R - g1 * Q_G + b1 * Q_B) - (r2 * Q_R - g2 * Q_G + b2 * Q_B);
-
-    YIQ_Y_WEIGHT * y_diff * y_diff + YIQ_I_WEIGHT * i_diff * i_diff + YIQ_Q_WEIGHT * q_diff * q_diff
+    match alpha_mode {
+        AlphaMode::OverBackground => color_delta_scalar(r1, g1, b1, r2, g2, b2, channels, metric),
+        AlphaMode::PremultipliedColor => {
+            let alpha_a = a_a / 255.0;
+            let alpha_b = b_a / 255.0;
+            color_delta_scalar(
+                a_r * alpha_a,
+                a_g * alpha_a,
+                a_b * alpha_a,
+                b_r * alpha_b,
+                b_g * alpha_b,
+                b_b * alpha_b,
+                channels,
+                metric,
+            )
+        }
+        AlphaMode::AlphaWeighted => {
+            let base = color_delta_scalar(r1, g1, b1, r2, g2, b2, channels, metric);
+            if channels.contains(ChannelMask::A) {
+                let alpha_diff = a_a - b_a;
+                base + ALPHA_DIFF_WEIGHT * alpha_diff * alpha_diff
+            } else {
+                base
+            }
+        }
+        AlphaMode::AlphaTest { threshold } => {
+            if !channels.contains(ChannelMask::A) {
+                color_delta_scalar(a_r, a_g, a_b, b_r, b_g, b_b, channels, metric)
+            } else {
+                let visible_a = a_a >= threshold as f32;
+                let visible_b = b_a >= threshold as f32;
+                if visible_a != visible_b {
+                    f32::MAX
+                } else if visible_a && visible_b {
+                    color_delta_scalar(a_r, a_g, a_b, b_r, b_g, b_b, channels, metric)
+                } else {
+                    // Both mutually invisible: no diff, regardless of raw color.
+                    0.0
+                }
+            }
+        }
+    }
 }
 
 /// Calculate brightness delta for antialiasing detection with SIMD optimization
 #[inline(always)]
-fn calculate_brightness_delta_fast(pixel_a: u32, pixel_b: u32) -> f32 {
+fn calculate_brightness_delta_fast(pixel_a: u32, pixel_b: u32, bg_color: [u8; 3]) -> f32 {
     // Use SIMD for single pixel calculations too
     let pixels_a = u32x4::from([pixel_a, 0, 0, 0]);
     let pixels_b = u32x4::from([pixel_b, 0, 0, 0]);
@@ -491,6 +1114,7 @@ fn calculate_brightness_delta_fast(pixel_a: u32, pixel_b: u32) -> f32 {
     let simd_y_r = f32x4::splat(Y_R);
     let simd_y_g = f32x4::splat(Y_G);
     let simd_y_b = f32x4::splat(Y_B);
+    let (bg_r, bg_g, bg_b) = get_simd_bg_constants_x4(bg_color);
 
     // Alpha blending
     let alpha_a = a_a / simd_255;
@@ -501,25 +1125,25 @@ fn calculate_brightness_delta_fast(pixel_a: u32, pixel_b: u32) -> f32 {
     let transparent_b = b_a.cmp_eq(simd_zero);
     let opaque_b = b_a.cmp_eq(simd_255);
 
-    let white_luma = simd_255 * (simd_y_r + simd_y_g + simd_y_b);
+    let bg_luma = bg_r * simd_y_r + bg_g * simd_y_g + bg_b * simd_y_b;
 
     let y1 = transparent_a.blend(
-        white_luma,
+        bg_luma,
         opaque_a.blend(
             a_r * simd_y_r + a_g * simd_y_g + a_b * simd_y_b,
-            (simd_255 + (a_r - simd_255) * alpha_a) * simd_y_r
-                + (simd_255 + (a_g - simd_255) * alpha_a) * simd_y_g
-                + (simd_255 + (a_b - simd_255) * alpha_a) * simd_y_b,
+            (bg_r + (a_r - bg_r) * alpha_a) * simd_y_r
+                + (bg_g + (a_g - bg_g) * alpha_a) * simd_y_g
+                + (bg_b + (a_b - bg_b) * alpha_a) * simd_y_b,
         ),
     );
 
     let y2 = transparent_b.blend(
-        white_luma,
+        bg_luma,
         opaque_b.blend(
             b_r * simd_y_r + b_g * simd_y_g + b_b * simd_y_b,
-            (simd_255 + (b_r - simd_255) * alpha_b) * simd_y_r
-                + (simd_255 + (b_g - simd_255) * alpha_b) * simd_y_g
-                + (simd_255 + (b_b - simd_255) * alpha_b) * simd_y_b,
+            (bg_r + (b_r - bg_r) * alpha_b) * simd_y_r
+                + (bg_g + (b_g - bg_g) * alpha_b) * simd_y_g
+                + (bg_b + (b_b - bg_b) * alpha_b) * simd_y_b,
         ),
     );
 
@@ -536,6 +1160,7 @@ fn is_pixel_antialiased_optimized(
     y: i32,
     width: i32,
     height: i32,
+    bg_color: [u8; 3],
 ) -> bool {
     // Early boundary check
     let is_edge = x == 0 || x == width - 1 || y == 0 || y == height - 1;
@@ -571,7 +1196,7 @@ fn is_pixel_antialiased_optimized(
                     return false;
                 }
             } else {
-                let delta = calculate_brightness_delta_fast(base_color, adjacent_color);
+                let delta = calculate_brightness_delta_fast(base_color, adjacent_color, bg_color);
                 if delta < min_delta {
                     min_delta = delta;
                     min_coord = (adj_x, adj_y);
@@ -648,12 +1273,13 @@ fn write_color(out: &mut [u8], pos: usize, color: &[u8; 3]) {
 }
 
 #[inline(always)]
-fn draw_gray_pixel_fast(img: &[u8], i: usize, alpha: f32, out: &mut [u8]) {
+fn draw_gray_pixel_fast(img: &[u8], i: usize, alpha: f32, bg_color: [u8; 3], out: &mut [u8]) {
     // Pre-compute luma using integer math where possible
     let y = (img[i] as f32 * Y_R + img[i + 1] as f32 * Y_G + img[i + 2] as f32 * Y_B) as u32;
+    let bg_luma = bg_color[0] as f32 * Y_R + bg_color[1] as f32 * Y_G + bg_color[2] as f32 * Y_B;
 
     let a = img[i + 3] as f32 * (1.0 / 255.0); // Multiply by reciprocal
-    let val = ((255.0 + (y as f32 - 255.0) * alpha * a).max(0.0).min(255.0)) as u8;
+    let val = ((bg_luma + (y as f32 - bg_luma) * alpha * a).max(0.0).min(255.0)) as u8;
 
     out[i] = val;
     out[i + 1] = val;
@@ -661,8 +1287,272 @@ fn draw_gray_pixel_fast(img: &[u8], i: usize, alpha: f32, out: &mut [u8]) {
     out[i + 3] = 255;
 }
 
+// === Resampling =========================================================================================
+
+fn resize_target_dims(w1: u32, h1: u32, w2: u32, h2: u32, policy: ResizePolicy) -> (u32, u32) {
+    match policy {
+        ResizePolicy::MatchLarger => (w1.max(w2), h1.max(h2)),
+        ResizePolicy::MatchSmaller => (w1.min(w2), h1.min(h2)),
+        ResizePolicy::To { width, height } => (width, height),
+    }
+}
+
+/// Support radius (in destination-pixel units, at a 1:1 scale) of each kernel.
+fn filter_support(filter: ResizeFilter) -> f32 {
+    match filter {
+        ResizeFilter::Box => 0.5,
+        ResizeFilter::Bilinear => 1.0,
+        ResizeFilter::Lanczos => 3.0,
+    }
+}
+
+/// Evaluate the resampling kernel at a distance `x` (in source-pixel units).
+fn filter_weight(filter: ResizeFilter, x: f32) -> f32 {
+    match filter {
+        ResizeFilter::Box => {
+            if x.abs() <= 0.5 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        ResizeFilter::Bilinear => {
+            let x = x.abs();
+            if x < 1.0 { 1.0 - x } else { 0.0 }
+        }
+        ResizeFilter::Lanczos => {
+            const A: f32 = 3.0;
+            if x == 0.0 {
+                1.0
+            } else if x.abs() < A {
+                let px = std::f32::consts::PI * x;
+                A * px.sin() * (px / A).sin() / (px * px)
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
+/// Resample one axis of an RGBA8 buffer, mapping each output pixel's center
+/// back into source coordinates and weighting the taps within the filter
+/// radius. For minification the radius is widened by `src/dst` so downscaling
+/// averages rather than aliases; out-of-bounds taps clamp to the last row/column.
+fn resample_axis(
+    src: &[u8],
+    src_len: usize,
+    other_len: usize,
+    dst_len: usize,
+    filter: ResizeFilter,
+    horizontal: bool,
+) -> Vec<u8> {
+    let mut dst = vec![0u8; if horizontal {
+        dst_len * other_len * 4
+    } else {
+        other_len * dst_len * 4
+    }];
+
+    if src_len == dst_len {
+        dst.copy_from_slice(src);
+        return dst;
+    }
+
+    let scale = src_len as f32 / dst_len as f32;
+    let filter_scale = scale.max(1.0);
+    let radius = (filter_support(filter) * filter_scale).ceil() as i64 + 1;
+
+    for d in 0..dst_len {
+        let center = (d as f32 + 0.5) * scale - 0.5;
+        let first = (center.floor() as i64) - radius;
+        let last = (center.floor() as i64) + radius;
+
+        let mut taps: Vec<(usize, f32)> = Vec::new();
+        let mut weight_sum = 0.0f32;
+        for s in first..=last {
+            let w = filter_weight(filter, (s as f32 - center) / filter_scale);
+            if w == 0.0 {
+                continue;
+            }
+            let clamped = s.clamp(0, src_len as i64 - 1) as usize;
+            taps.push((clamped, w));
+            weight_sum += w;
+        }
+        if weight_sum == 0.0 {
+            continue;
+        }
+
+        for o in 0..other_len {
+            let mut sum = [0f32; 4];
+            for &(s, w) in &taps {
+                let idx = if horizontal {
+                    (o * src_len + s) * 4
+                } else {
+                    (s * other_len + o) * 4
+                };
+                for c in 0..4 {
+                    sum[c] += src[idx + c] as f32 * w;
+                }
+            }
+            let didx = if horizontal {
+                (o * dst_len + d) * 4
+            } else {
+                (d * other_len + o) * 4
+            };
+            for c in 0..4 {
+                dst[didx + c] = (sum[c] / weight_sum).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+
+    dst
+}
+
+/// Resample an RGBA8 buffer to `(dst_w, dst_h)` using a separable two-pass filter:
+/// a horizontal pass into an intermediate buffer, then a vertical pass.
+fn resample_rgba(
+    src: &[u8],
+    src_w: u32,
+    src_h: u32,
+    dst_w: u32,
+    dst_h: u32,
+    filter: ResizeFilter,
+) -> Vec<u8> {
+    if src_w == dst_w && src_h == dst_h {
+        return src.to_vec();
+    }
+    let horizontal = resample_axis(
+        src,
+        src_w as usize,
+        src_h as usize,
+        dst_w as usize,
+        filter,
+        true,
+    );
+    resample_axis(
+        &horizontal,
+        src_h as usize,
+        dst_w as usize,
+        dst_h as usize,
+        filter,
+        false,
+    )
+}
+
+// === Blurring ============================================================================================
+
+/// Build the normalized 1-D kernel for a `BlurConfig`.
+fn blur_kernel(config: BlurConfig) -> Vec<f32> {
+    let r = config.radius as i64;
+    let mut kernel: Vec<f32> = match config.kind {
+        BlurKind::Box => vec![1.0; (2 * r + 1) as usize],
+        BlurKind::Gaussian { sigma } => (-r..=r)
+            .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+            .collect(),
+    };
+    let sum: f32 = kernel.iter().sum();
+    for w in &mut kernel {
+        *w /= sum;
+    }
+    kernel
+}
+
+/// One axis of a separable blur over an RGBA8 buffer, accumulating 8 adjacent
+/// output pixels at a time with `f32x8`; out-of-bounds taps clamp to the border
+/// pixel, and the four channels stay independent.
+fn blur_axis(src: &[u8], w: usize, h: usize, kernel: &[f32], horizontal: bool) -> Vec<u8> {
+    let radius = (kernel.len() / 2) as i64;
+    let mut dst = vec![0u8; w * h * 4];
+
+    let (outer, inner) = if horizontal { (h, w) } else { (w, h) };
+
+    for o in 0..outer {
+        let mut i = 0usize;
+        while i < inner {
+            let lanes = (inner - i).min(8);
+            let mut sum = [f32x8::splat(0.0); 4];
+
+            for (k, &weight) in kernel.iter().enumerate() {
+                let offset = k as i64 - radius;
+                let mut taps = [[0f32; 8]; 4];
+                for lane in 0..lanes {
+                    let pos = (i + lane) as i64 + offset;
+                    let clamped = pos.clamp(0, inner as i64 - 1) as usize;
+                    let (x, y) = if horizontal { (clamped, o) } else { (o, clamped) };
+                    let idx = (y * w + x) * 4;
+                    for (c, tap) in taps.iter_mut().enumerate() {
+                        tap[lane] = src[idx + c] as f32;
+                    }
+                }
+                let weight_v = f32x8::splat(weight);
+                for c in 0..4 {
+                    sum[c] += f32x8::from(taps[c]) * weight_v;
+                }
+            }
+
+            let results: [[f32; 8]; 4] = sum.map(|s| s.into());
+            for lane in 0..lanes {
+                let (x, y) = if horizontal { (i + lane, o) } else { (o, i + lane) };
+                let idx = (y * w + x) * 4;
+                for (c, result) in results.iter().enumerate() {
+                    dst[idx + c] = result[lane].round().clamp(0.0, 255.0) as u8;
+                }
+            }
+            i += lanes;
+        }
+    }
+
+    dst
+}
+
+/// Separable blur of an RGBA8 buffer: a horizontal pass into a scratch buffer,
+/// then a vertical pass.
+fn blur_rgba(src: &[u8], w: usize, h: usize, config: BlurConfig) -> Vec<u8> {
+    let kernel = blur_kernel(config);
+    let horizontal = blur_axis(src, w, h, &kernel, true);
+    blur_axis(&horizontal, w, h, &kernel, false)
+}
+
 // === Image decoding and main diff functions ============================================================
 
+/// Reconcile two RGBA8 buffers of possibly-different sizes into a common size,
+/// resampling via `opts.resize` if set, or erroring like before if not. The
+/// `DiffDetail` is `Some` whenever the inputs needed reconciling at all, so
+/// callers can surface it even though `diff_rgba` itself never sees the
+/// pre-reconciliation sizes.
+fn reconcile_dims(
+    img1: image::RgbaImage,
+    img2: image::RgbaImage,
+    opts: &DiffOptions,
+) -> Result<(Vec<u8>, Vec<u8>, u32, u32, Option<DiffDetail>), Box<dyn std::error::Error>> {
+    let (w1, h1) = img1.dimensions();
+    let (w2, h2) = img2.dimensions();
+
+    if w1 == w2 && h1 == h2 {
+        return Ok((img1.into_raw(), img2.into_raw(), w1, h1, None));
+    }
+
+    let mismatch = DiffDetail::DimensionMismatch {
+        nominal_width: w1,
+        nominal_height: h1,
+        actual_width: w2,
+        actual_height: h2,
+    };
+
+    match opts.resize {
+        Some(policy) => {
+            let (dst_w, dst_h) = resize_target_dims(w1, h1, w2, h2, policy);
+            let buf1 = resample_rgba(img1.as_raw(), w1, h1, dst_w, dst_h, opts.resize_filter);
+            let buf2 = resample_rgba(img2.as_raw(), w2, h2, dst_w, dst_h, opts.resize_filter);
+            Ok((buf1, buf2, dst_w, dst_h, Some(mismatch)))
+        }
+        None => Err(format!(
+            "Images must have equal dimensions. Image 1: {:?}x{:?}, Image 2: {:?}x{:?}",
+            w1, h1, w2, h2
+        )
+        .into()),
+    }
+}
+
 /// Compare two images from file paths
 pub fn diff_images<P: AsRef<std::path::Path>>(
     img1_path: P,
@@ -675,24 +1565,13 @@ pub fn diff_images<P: AsRef<std::path::Path>>(
     let img1 = ImageReader::open(img1_path)?.decode()?;
     let img2 = ImageReader::open(img2_path)?.decode()?;
 
-    // Check dimensions before conversion
-    if img1.width() != img2.width() || img1.height() != img2.height() {
-        return Err(format!(
-            "Images must have equal dimensions. Image 1: {:?}x{:?}, Image 2: {:?}x{:?}",
-            img1.width(),
-            img2.width(),
-            img1.height(),
-            img2.height()
-        )
-        .into());
+    let opts = opts.unwrap_or_default();
+    let (buf1, buf2, w, h, mismatch) = reconcile_dims(img1.to_rgba8(), img2.to_rgba8(), &opts)?;
+    let mut result = diff_rgba(&buf1, &buf2, w, h, Some(opts));
+    if let Some(detail) = mismatch {
+        result.details.insert(0, detail);
     }
-
-    // Convert to RGBA8
-    let img1 = img1.to_rgba8();
-    let img2 = img2.to_rgba8();
-
-    let (w, h) = img1.dimensions();
-    Ok(diff_rgba(img1.as_raw(), img2.as_raw(), w, h, opts))
+    Ok(result)
 }
 
 /// Compare two images from byte data
@@ -710,22 +1589,11 @@ pub fn diff_bytes(
         .with_guessed_format()?
         .decode()?;
 
-    // Check dimensions before conversion
-    if img1.width() != img2.width() || img1.height() != img2.height() {
-        return Err(format!(
-            "Images must have equal dimensions. Image 1: {:?}x{:?}, Image 2: {:?}x{:?}",
-            img1.width(),
-            img2.width(),
-            img1.height(),
-            img2.height()
-        )
-        .into());
+    let opts = opts.unwrap_or_default();
+    let (buf1, buf2, w, h, mismatch) = reconcile_dims(img1.to_rgba8(), img2.to_rgba8(), &opts)?;
+    let mut result = diff_rgba(&buf1, &buf2, w, h, Some(opts));
+    if let Some(detail) = mismatch {
+        result.details.insert(0, detail);
     }
-
-    // Convert to RGBA8
-    let img1 = img1.to_rgba8();
-    let img2 = img2.to_rgba8();
-
-    let (w, h) = img1.dimensions();
-    Ok(diff_rgba(img1.as_raw(), img2.as_raw(), w, h, opts))
+    Ok(result)
 }
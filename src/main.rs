@@ -1,17 +1,68 @@
 use image::{ImageBuffer, RgbaImage};
-use rsdiff::{DiffOptions, diff_images};
+use rsdiff::{AlphaMode, DiffDetail, DiffOptions, Metric, diff_images};
 use serde_json;
+use std::collections::BTreeSet;
 use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(serde::Serialize)]
 struct CliResult {
+    /// Identifies which input pair this result is for: the relative path in
+    /// `--recursive` mode, the first image's path when multiple pairs are
+    /// given on the command line, or `None` for a lone single-pair comparison.
+    file: Option<String>,
+    /// Path of the "expected"/baseline image, modeled after havocompare's
+    /// `Difference::nominal_file`. Set even when the comparison failed.
+    nominal_file: String,
+    /// Path of the "actual"/candidate image being compared against `nominal_file`.
+    actual_file: String,
     success: bool,
     diff_count: u32,
     total_pixels: u32,
     diff_percentage: f64,
+    width: u32,
+    height: u32,
+    /// (min_x, min_y, max_x, max_y) over every pixel that counted toward
+    /// `diff_count`. `None` on failure or an exact match.
+    bounding_box: Option<(u32, u32, u32, u32)>,
     output_path: Option<String>,
     error: Option<String>,
+    /// Typed reasons the two images differ (dimension mismatch, per-channel
+    /// differences, clustered regions). Empty on failure or an exact match.
+    details: Vec<DiffDetail>,
+}
+
+/// Aggregate report produced by `--recursive` mode.
+#[derive(serde::Serialize)]
+struct DirDiffResult {
+    files: Vec<CliResult>,
+    /// Relative paths present under the first directory but not the second.
+    only_in_first: Vec<String>,
+    /// Relative paths present under the second directory but not the first.
+    only_in_second: Vec<String>,
+}
+
+/// Process exit code for a real failure: missing/unreadable input, a decode
+/// error, or a failed save. Distinct from `EXIT_DIFF_EXCEEDS_THRESHOLD` so CI
+/// callers can tell "rsdiff couldn't run" from "rsdiff ran and the images
+/// differ too much".
+const EXIT_ERROR: i32 = 1;
+/// Process exit code when every comparison succeeded but at least one
+/// exceeded `--fail-on`/`--max-diff-pixels`.
+const EXIT_DIFF_EXCEEDS_THRESHOLD: i32 = 2;
+
+/// Whether a successful comparison's diff exceeds the configured CI gate.
+/// A failed comparison (`!result.success`) is never "exceeded" here — that's
+/// reported via `EXIT_ERROR` instead.
+fn exceeds_threshold(result: &CliResult, fail_on: Option<f64>, max_diff_pixels: Option<u32>) -> bool {
+    result.success
+        && (fail_on.is_some_and(|limit| result.diff_percentage > limit)
+            || max_diff_pixels.is_some_and(|limit| result.diff_count > limit))
+}
+
+fn build_output_image(output_data: &[u8], width: u32, height: u32) -> Result<RgbaImage, Box<dyn std::error::Error>> {
+    ImageBuffer::from_raw(width, height, output_data.to_vec())
+        .ok_or_else(|| "Failed to create image buffer from diff output".into())
 }
 
 fn save_output_image(
@@ -20,53 +71,426 @@ fn save_output_image(
     height: u32,
     path: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let img_buffer: RgbaImage = ImageBuffer::from_raw(width, height, output_data.to_vec())
-        .ok_or("Failed to create image buffer from diff output")?;
-    img_buffer.save(path)?;
+    build_output_image(output_data, width, height)?.save(path)?;
     Ok(())
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// Encode the diff output as a PNG in memory and write the raw bytes to stdout,
+/// for `--stdout`/`-c` pipeline usage (`rsdiff a.png b.png -c | other-tool`).
+/// Unlike `save_output_image` this never touches the filesystem.
+fn write_output_image_to_stdout(
+    output_data: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    let img_buffer = build_output_image(output_data, width, height)?;
+    let mut bytes = Vec::new();
+    img_buffer.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)?;
+    std::io::stdout().write_all(&bytes)?;
+    Ok(())
+}
+
+/// Print a line of the textual/JSON summary. When `--stdout`/`-c` is in play the
+/// diff PNG itself owns stdout, so the summary is redirected to stderr instead.
+fn print_summary_line(write_stdout: bool, line: &str) {
+    if write_stdout {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Expand a single CLI argument into the file paths it names. Arguments
+/// without a `*` pass through unchanged (including nonexistent ones, so
+/// `diff_one_pair`'s existence check still produces a proper error). An
+/// argument with a `*` in its last path component is matched against that
+/// component's siblings by prefix/suffix around the star; only one `*` per
+/// pattern is supported (no recursive `**`).
+fn expand_glob(pattern: &str) -> Vec<String> {
+    if !pattern.contains('*') {
+        return vec![pattern.to_string()];
+    }
+
+    let path = Path::new(pattern);
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let file_pattern = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (prefix, suffix) = file_pattern.split_once('*').unwrap_or((&file_pattern, ""));
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut matches: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.len() >= prefix.len() + suffix.len()
+                && name.starts_with(prefix)
+                && name.ends_with(suffix)
+            {
+                Some(dir.join(&name).to_string_lossy().into_owned())
+            } else {
+                None
+            }
+        })
+        .collect();
+    matches.sort();
+    matches
+}
+
+/// Parse one `--ignore-region=x,y,width,height` value into its four components.
+fn parse_ignore_region(raw: &str) -> Option<(u32, u32, u32, u32)> {
+    let parts: Vec<&str> = raw.split(',').collect();
+    if parts.len() != 4 {
+        return None;
+    }
+    Some((
+        parts[0].parse().ok()?,
+        parts[1].parse().ok()?,
+        parts[2].parse().ok()?,
+        parts[3].parse().ok()?,
+    ))
+}
 
-    // Parse command line arguments
-    if args.len() < 3 {
-        let error_result = CliResult {
-            success: false,
-            diff_count: 0,
-            total_pixels: 0,
-            diff_percentage: 0.0,
-            output_path: None,
-            error: Some("Usage: rsdiff <image1> <image2> [options]".to_string()),
+/// Derive the filename a pair's diff PNG is written under in `--output-dir`
+/// mode: the second (actual) image's own name, so a diff tree mirrors the
+/// names the build under test produced, falling back to the first image's
+/// name if the second has none.
+fn derive_output_name(img1_path: &str, img2_path: &str) -> String {
+    Path::new(img2_path)
+        .file_name()
+        .or_else(|| Path::new(img1_path).file_name())
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "diff.png".to_string())
+}
+
+/// Join `dir` and `name`, appending a `_2`, `_3`, ... suffix before the
+/// extension if that path was already handed out this run. Needed because
+/// `derive_output_name` only looks at a pair's own filenames, so two pairs
+/// from different source directories that happen to share a basename would
+/// otherwise derive the same output path and silently overwrite each other.
+fn dedupe_output_path(dir: &str, name: &str, used: &mut std::collections::HashSet<String>) -> String {
+    let first = Path::new(dir).join(name).to_string_lossy().into_owned();
+    if used.insert(first.clone()) {
+        return first;
+    }
+
+    let path = Path::new(name);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| name.to_string());
+    let ext = path.extension().map(|e| e.to_string_lossy().into_owned());
+
+    let mut n = 2;
+    loop {
+        let candidate_name = match &ext {
+            Some(ext) => format!("{stem}_{n}.{ext}"),
+            None => format!("{stem}_{n}"),
         };
+        let candidate = Path::new(dir).join(candidate_name).to_string_lossy().into_owned();
+        if used.insert(candidate.clone()) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
 
-        if args.contains(&"--json".to_string()) {
-            println!("{}", serde_json::to_string(&error_result).unwrap());
+/// Recursively collect every file under `root`, returned as paths relative to it.
+fn walk_relative_files(root: &Path) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    walk_relative_files_into(root, Path::new(""), &mut out);
+    out
+}
+
+fn walk_relative_files_into(root: &Path, rel: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(root.join(rel)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let rel_path = rel.join(entry.file_name());
+        if entry.path().is_dir() {
+            walk_relative_files_into(root, &rel_path, out);
         } else {
-            eprintln!("Usage: {} <image1> <image2> [options]", args[0]);
-            eprintln!("");
-            eprintln!("Options:");
-            eprintln!("  --output=<path>     Save diff output to specified path");
-            eprintln!("  --=<path> Save diff output to specified path (alias)");
-            eprintln!("  --json              Output results in JSON format");
-            eprintln!("  --threshold=<value> Difference threshold (default: 0.1)");
-            eprintln!("  --include-aa        Include anti-aliasing detection");
-            eprintln!("  --alpha=<value>     Alpha value for output (default: 0.1)");
+            out.push(rel_path);
+        }
+    }
+}
+
+/// Diff a single pair of image files, never exiting the process: errors (missing
+/// file, decode failure, save failure) are reported in the returned `CliResult`
+/// so batch callers can keep going after one pair fails.
+fn diff_one_pair(
+    img1_path: &str,
+    img2_path: &str,
+    output_path: Option<&str>,
+    write_stdout: bool,
+    opts: DiffOptions,
+) -> CliResult {
+    let error_result = |error: String| CliResult {
+        file: None,
+        nominal_file: img1_path.to_string(),
+        actual_file: img2_path.to_string(),
+        success: false,
+        diff_count: 0,
+        total_pixels: 0,
+        diff_percentage: 0.0,
+        width: 0,
+        height: 0,
+        bounding_box: None,
+        output_path: None,
+        error: Some(error),
+        details: Vec::new(),
+    };
+
+    if !Path::new(img1_path).exists() {
+        return error_result(format!("Image 1 does not exist: {}", img1_path));
+    }
+
+    if !Path::new(img2_path).exists() {
+        return error_result(format!("Image 2 does not exist: {}", img2_path));
+    }
+
+    match diff_images(img1_path, img2_path, Some(opts)) {
+        Ok(result) => {
+            let total_pixels = result.width * result.height;
+            let diff_percentage = (result.diff_count as f64 / total_pixels as f64) * 100.0;
+
+            let final_output_path = if let Some(path) = output_path {
+                match save_output_image(&result.output, result.width, result.height, path) {
+                    Ok(_) => Some(path.to_string()),
+                    Err(e) => return error_result(format!("Failed to save output: {}", e)),
+                }
+            } else {
+                None
+            };
+
+            if write_stdout {
+                if let Err(e) = write_output_image_to_stdout(&result.output, result.width, result.height) {
+                    return error_result(format!("Failed to write diff PNG to stdout: {}", e));
+                }
+            }
+
+            CliResult {
+                file: None,
+                nominal_file: img1_path.to_string(),
+                actual_file: img2_path.to_string(),
+                success: true,
+                diff_count: result.diff_count,
+                total_pixels,
+                diff_percentage,
+                width: result.width,
+                height: result.height,
+                bounding_box: result.bounding_box,
+                output_path: final_output_path,
+                error: None,
+                details: result.details,
+            }
+        }
+        Err(e) => error_result(e.to_string()),
+    }
+}
+
+/// Walk two structurally similar directory trees, diffing every file present on
+/// both sides and mirroring the diff PNG under `diff_dir` (if set). Exits the
+/// process: `EXIT_ERROR` if any pair failed to diff, `EXIT_DIFF_EXCEEDS_THRESHOLD`
+/// if every pair diffed cleanly but one exceeded `fail_on`/`max_diff_pixels`,
+/// otherwise falls through to the normal 0 exit.
+fn run_recursive(
+    dir1: &str,
+    dir2: &str,
+    diff_dir: Option<&str>,
+    opts: &DiffOptions,
+    json_output: bool,
+    fail_on: Option<f64>,
+    max_diff_pixels: Option<u32>,
+) {
+    let root1 = Path::new(dir1);
+    let root2 = Path::new(dir2);
+
+    for (label, root) in [("first", root1), ("second", root2)] {
+        if !root.is_dir() {
+            let error = format!("{} directory does not exist: {}", label, root.display());
+            if json_output {
+                println!("{}", serde_json::to_string(&serde_json::json!({ "error": error })).unwrap());
+            } else {
+                eprintln!("Error: {}", error);
+            }
+            std::process::exit(EXIT_ERROR);
+        }
+    }
+
+    let files1: BTreeSet<PathBuf> = walk_relative_files(root1).into_iter().collect();
+    let files2: BTreeSet<PathBuf> = walk_relative_files(root2).into_iter().collect();
+
+    let only_in_first: Vec<String> = files1
+        .difference(&files2)
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect();
+    let only_in_second: Vec<String> = files2
+        .difference(&files1)
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect();
+
+    let mut files = Vec::new();
+    for rel in files1.intersection(&files2) {
+        let path1 = root1.join(rel);
+        let path2 = root2.join(rel);
+
+        let output_path = diff_dir.map(|dir| {
+            let out = Path::new(dir).join(rel);
+            if let Some(parent) = out.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            out.to_string_lossy().into_owned()
+        });
+
+        let mut result = diff_one_pair(
+            &path1.to_string_lossy(),
+            &path2.to_string_lossy(),
+            output_path.as_deref(),
+            false,
+            opts.clone(),
+        );
+        result.file = Some(rel.to_string_lossy().into_owned());
+        files.push(result);
+    }
+
+    let dir_result = DirDiffResult {
+        files,
+        only_in_first,
+        only_in_second,
+    };
+
+    if json_output {
+        println!("{}", serde_json::to_string(&dir_result).unwrap());
+    } else {
+        for result in &dir_result.files {
+            let name = result.file.as_deref().unwrap_or("?");
+            if result.success {
+                println!(
+                    "{}: {} different pixels ({:.2}%)",
+                    name, result.diff_count, result.diff_percentage
+                );
+            } else {
+                println!(
+                    "{}: error - {}",
+                    name,
+                    result.error.as_deref().unwrap_or("unknown error")
+                );
+            }
         }
-        std::process::exit(1);
+        println!(
+            "Only in {}: {} file(s)",
+            dir1,
+            dir_result.only_in_first.len()
+        );
+        println!(
+            "Only in {}: {} file(s)",
+            dir2,
+            dir_result.only_in_second.len()
+        );
     }
 
-    let img1_path = &args[1];
-    let img2_path = &args[2];
+    if dir_result.files.iter().any(|r| !r.success) {
+        std::process::exit(EXIT_ERROR);
+    }
+    if dir_result
+        .files
+        .iter()
+        .any(|r| exceeds_threshold(r, fail_on, max_diff_pixels))
+    {
+        std::process::exit(EXIT_DIFF_EXCEEDS_THRESHOLD);
+    }
+}
+
+fn print_usage_and_exit(args: &[String]) -> ! {
+    let error_result = CliResult {
+        file: None,
+        nominal_file: String::new(),
+        actual_file: String::new(),
+        success: false,
+        diff_count: 0,
+        total_pixels: 0,
+        diff_percentage: 0.0,
+        width: 0,
+        height: 0,
+        bounding_box: None,
+        output_path: None,
+        error: Some("Usage: rsdiff <image1> <image2> [<image1b> <image2b> ...] [options]".to_string()),
+        details: Vec::new(),
+    };
+
+    if args.contains(&"--json".to_string()) {
+        println!("{}", serde_json::to_string(&error_result).unwrap());
+    } else {
+        eprintln!(
+            "Usage: {} <image1> <image2> [<image1b> <image2b> ...] [options]",
+            args[0]
+        );
+        eprintln!("       {} <dir1> <dir2> --recursive [options]", args[0]);
+        eprintln!();
+        eprintln!("Options:");
+        eprintln!("  --output=<path>     Save diff output to specified path (single pair only)");
+        eprintln!("  --output-dir=<path> Save each pair's diff output into this directory,");
+        eprintln!("                      named after the second image in the pair");
+        eprintln!("  --json              Output results in JSON format");
+        eprintln!("  --threshold=<value> Difference threshold (default: 0.1)");
+        eprintln!("  --include-aa        Include anti-aliasing detection");
+        eprintln!("  --alpha=<value>     Alpha value for output (default: 0.1)");
+        eprintln!("  --recursive         Treat the two inputs as directories to compare");
+        eprintln!("  --diff-dir=<path>   Directory to mirror diff PNGs into (with --recursive)");
+        eprintln!("  --fail-on=<percent> Exit {} if any pair's diff_percentage exceeds this", EXIT_DIFF_EXCEEDS_THRESHOLD);
+        eprintln!("  --max-diff-pixels=<n> Exit {} if any pair's diff_count exceeds this", EXIT_DIFF_EXCEEDS_THRESHOLD);
+        eprintln!("  --stdout, -c        Write the diff PNG to stdout (single pair only);");
+        eprintln!("                      the summary is redirected to stderr");
+        eprintln!("  --ignore-region=x,y,width,height");
+        eprintln!("                      Exclude a rectangle from comparison (repeatable)");
+        eprintln!("  --metric=yiq|rgb    Color-distance metric threshold is scaled against");
+        eprintln!("                      (default: yiq)");
+    }
+    std::process::exit(EXIT_ERROR);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
     let json_output = args.contains(&"--json".to_string());
+    let recursive = args.contains(&"--recursive".to_string());
+
+    let positional: Vec<&String> = args[1..]
+        .iter()
+        .filter(|a| !a.starts_with("--") && a.as_str() != "-c")
+        .collect();
+
+    if positional.len() < 2 {
+        print_usage_and_exit(&args);
+    }
 
     // Parse options
     let mut threshold = 0.1;
     let mut include_aa = false;
     let mut alpha = 0.1;
     let mut output_path: Option<String> = None;
+    let mut output_dir: Option<String> = None;
+    let mut diff_dir: Option<String> = None;
+    let mut fail_on: Option<f64> = None;
+    let mut max_diff_pixels: Option<u32> = None;
+    let mut write_stdout = false;
+    let mut ignore_regions: Vec<(u32, u32, u32, u32)> = Vec::new();
+    let mut metric = Metric::Yiq;
 
-    for arg in &args[3..] {
+    for arg in args[1..]
+        .iter()
+        .filter(|a| a.starts_with("--") || a.as_str() == "-c")
+    {
         if arg.starts_with("--threshold=") {
             if let Ok(val) = arg.split('=').nth(1).unwrap_or("0.1").parse::<f32>() {
                 threshold = val;
@@ -75,50 +499,62 @@ fn main() {
             if let Ok(val) = arg.split('=').nth(1).unwrap_or("0.1").parse::<f32>() {
                 alpha = val;
             }
+        } else if arg.starts_with("--output-dir=") {
+            output_dir = arg.split('=').nth(1).map(|s| s.to_string());
         } else if arg.starts_with("--output=") {
             output_path = arg.split('=').nth(1).map(|s| s.to_string());
+        } else if arg.starts_with("--diff-dir=") {
+            diff_dir = arg.split('=').nth(1).map(|s| s.to_string());
+        } else if arg.starts_with("--fail-on=") {
+            let raw = arg.split('=').nth(1).unwrap_or("");
+            match raw.parse::<f64>() {
+                Ok(val) => fail_on = Some(val),
+                Err(_) => {
+                    eprintln!("Error: invalid --fail-on value '{}' (expected a number)", raw);
+                    std::process::exit(EXIT_ERROR);
+                }
+            }
+        } else if arg.starts_with("--max-diff-pixels=") {
+            let raw = arg.split('=').nth(1).unwrap_or("");
+            match raw.parse::<u32>() {
+                Ok(val) => max_diff_pixels = Some(val),
+                Err(_) => {
+                    eprintln!(
+                        "Error: invalid --max-diff-pixels value '{}' (expected a non-negative integer)",
+                        raw
+                    );
+                    std::process::exit(EXIT_ERROR);
+                }
+            }
+        } else if arg.starts_with("--ignore-region=") {
+            let raw = arg.split('=').nth(1).unwrap_or("");
+            match parse_ignore_region(raw) {
+                Some(region) => ignore_regions.push(region),
+                None => {
+                    eprintln!(
+                        "Error: invalid --ignore-region value '{}' (expected x,y,width,height)",
+                        raw
+                    );
+                    std::process::exit(EXIT_ERROR);
+                }
+            }
+        } else if arg.starts_with("--metric=") {
+            let raw = arg.split('=').nth(1).unwrap_or("");
+            match raw {
+                "yiq" => metric = Metric::Yiq,
+                "rgb" => metric = Metric::Rgb,
+                _ => {
+                    eprintln!("Error: invalid --metric value '{}' (expected 'yiq' or 'rgb')", raw);
+                    std::process::exit(EXIT_ERROR);
+                }
+            }
         } else if arg == "--include-aa" {
             include_aa = true;
+        } else if arg == "--stdout" || arg == "-c" {
+            write_stdout = true;
         }
     }
 
-    // Check if input files exist
-    if !Path::new(img1_path).exists() {
-        let error_result = CliResult {
-            success: false,
-            diff_count: 0,
-            total_pixels: 0,
-            diff_percentage: 0.0,
-            output_path: None,
-            error: Some(format!("Image 1 does not exist: {}", img1_path)),
-        };
-
-        if json_output {
-            println!("{}", serde_json::to_string(&error_result).unwrap());
-        } else {
-            eprintln!("Error: Image 1 does not exist: {}", img1_path);
-        }
-        std::process::exit(1);
-    }
-
-    if !Path::new(img2_path).exists() {
-        let error_result = CliResult {
-            success: false,
-            diff_count: 0,
-            total_pixels: 0,
-            diff_percentage: 0.0,
-            output_path: None,
-            error: Some(format!("Image 2 does not exist: {}", img2_path)),
-        };
-
-        if json_output {
-            println!("{}", serde_json::to_string(&error_result).unwrap());
-        } else {
-            eprintln!("Error: Image 2 does not exist: {}", img2_path);
-        }
-        std::process::exit(1);
-    }
-
     // Configure diff options
     let opts = DiffOptions {
         threshold,
@@ -127,80 +563,156 @@ fn main() {
         aa_color: [255, 255, 0],   // Yellow for anti-aliased pixels
         diff_color: [255, 0, 255], // Magenta for different pixels
         diff_color_alt: None,
+        bg_color: [255, 255, 255], // White
+        alpha_mode: AlphaMode::OverBackground,
+        metric,
+        region_connectivity: rsdiff::Connectivity::Eight,
+        resize: None,
+        resize_filter: rsdiff::ResizeFilter::Bilinear,
+        channels: rsdiff::ChannelMask::ALL,
+        per_channel_output: false,
+        prefilter: None,
+        ignore_regions,
     };
 
-    // Start timing
+    if write_stdout && recursive {
+        eprintln!("Error: --stdout/-c cannot be used with --recursive");
+        std::process::exit(EXIT_ERROR);
+    }
 
-    // Compare the images
-    match diff_images(img1_path, img2_path, Some(opts)) {
-        Ok(result) => {
-            let total_pixels = result.width * result.height;
-            let diff_percentage = (result.diff_count as f64 / total_pixels as f64) * 100.0;
+    if recursive {
+        run_recursive(
+            positional[0],
+            positional[1],
+            diff_dir.as_deref(),
+            &opts,
+            json_output,
+            fail_on,
+            max_diff_pixels,
+        );
+        return;
+    }
 
-            // Save output if path is provided
-            let final_output_path = if let Some(ref path) = output_path {
-                match save_output_image(&result.output, result.width, result.height, path) {
-                    Ok(_) => Some(path.clone()),
-                    Err(e) => {
-                        let error_result = CliResult {
-                            success: false,
-                            diff_count: 0,
-                            total_pixels: 0,
-                            diff_percentage: 0.0,
-                            output_path: None,
-                            error: Some(format!("Failed to save output: {}", e)),
-                        };
-
-                        if json_output {
-                            println!("{}", serde_json::to_string(&error_result).unwrap());
-                        } else {
-                            eprintln!("Error: Failed to save output: {}", e);
-                        }
-                        std::process::exit(1);
-                    }
-                }
-            } else {
-                None
-            };
+    if positional.len() % 2 != 0 {
+        let error = format!(
+            "expected image paths in pairs (<image1> <image2> [<image1b> <image2b> ...]), got {}",
+            positional.len()
+        );
+        if json_output {
+            println!(
+                "{}",
+                serde_json::to_string(&serde_json::json!({ "error": error })).unwrap()
+            );
+        } else {
+            eprintln!("Error: {}", error);
+        }
+        std::process::exit(EXIT_ERROR);
+    }
 
-            let cli_result = CliResult {
-                success: true,
-                diff_count: result.diff_count,
-                total_pixels,
-                diff_percentage,
-                output_path: final_output_path.clone(),
-                error: None,
-            };
+    // Each consecutive pair of CLI arguments is a "before"/"after" pattern;
+    // expand both sides and zip them by sorted order, so `before/*.png
+    // after/*.png` diffs the Nth match on one side against the Nth on the
+    // other instead of flattening both sides into a single list.
+    let mut pairs: Vec<(String, String)> = Vec::new();
+    for slot in positional.chunks(2) {
+        let left = expand_glob(slot[0]);
+        let right = expand_glob(slot[1]);
+        if left.len() != right.len() {
+            eprintln!(
+                "Error: '{}' matched {} file(s) but '{}' matched {} file(s)",
+                slot[0],
+                left.len(),
+                slot[1],
+                right.len()
+            );
+            std::process::exit(EXIT_ERROR);
+        }
+        pairs.extend(left.into_iter().zip(right));
+    }
 
-            if json_output {
-                println!("{}", serde_json::to_string(&cli_result).unwrap());
-            } else {
-                println!("Diff completed successfully!");
-                println!("Image dimensions: {}x{}", result.width, result.height);
-                println!("Different pixels: {}", result.diff_count);
-                println!("Total pixels: {}", total_pixels);
-                println!("Difference percentage: {:.2}%", diff_percentage);
-                if let Some(path) = final_output_path {
-                    println!("Output saved to: {}", path);
-                }
-            }
+    if output_path.is_some() && pairs.len() > 1 {
+        eprintln!("Warning: --output is ignored with multiple pairs; use --output-dir instead");
+        output_path = None;
+    }
+
+    if write_stdout && pairs.len() != 1 {
+        eprintln!("Error: --stdout/-c only supports a single image pair");
+        std::process::exit(EXIT_ERROR);
+    }
+
+    let mut results: Vec<CliResult> = Vec::new();
+    let mut any_failed = false;
+    let mut used_output_names: std::collections::HashSet<String> = std::collections::HashSet::new();
+
+    for (img1_path, img2_path) in &pairs {
+        let derived_path = output_dir.as_ref().map(|dir| {
+            let _ = std::fs::create_dir_all(dir);
+            let name = derive_output_name(img1_path, img2_path);
+            dedupe_output_path(dir, &name, &mut used_output_names)
+        });
+        let this_output_path = if pairs.len() == 1 {
+            output_path.clone().or(derived_path)
+        } else {
+            derived_path
+        };
+
+        let mut result = diff_one_pair(
+            img1_path,
+            img2_path,
+            this_output_path.as_deref(),
+            write_stdout,
+            opts.clone(),
+        );
+        if pairs.len() > 1 {
+            result.file = Some(img1_path.to_string());
         }
-        Err(e) => {
-            let error_result = CliResult {
-                success: false,
-                diff_count: 0,
-                total_pixels: 0,
-                diff_percentage: 0.0,
-                output_path: None,
-                error: Some(e.to_string()),
-            };
+        any_failed |= !result.success;
+        results.push(result);
+    }
 
-            if json_output {
-                println!("{}", serde_json::to_string(&error_result).unwrap());
+    if json_output {
+        let json = if results.len() == 1 {
+            serde_json::to_string(&results[0]).unwrap()
+        } else {
+            serde_json::to_string(&results).unwrap()
+        };
+        print_summary_line(write_stdout, &json);
+    } else {
+        for result in &results {
+            let label = result.file.as_deref();
+            let prefix = label.map(|l| format!("{}: ", l)).unwrap_or_default();
+            if result.success {
+                print_summary_line(write_stdout, &format!("{}Diff completed successfully!", prefix));
+                print_summary_line(
+                    write_stdout,
+                    &format!("Image dimensions: {}x{}", result.width, result.height),
+                );
+                print_summary_line(write_stdout, &format!("Different pixels: {}", result.diff_count));
+                print_summary_line(write_stdout, &format!("Total pixels: {}", result.total_pixels));
+                print_summary_line(
+                    write_stdout,
+                    &format!("Difference percentage: {:.2}%", result.diff_percentage),
+                );
+                if let Some(path) = &result.output_path {
+                    print_summary_line(write_stdout, &format!("Output saved to: {}", path));
+                }
             } else {
-                eprintln!("Error: {}", e);
+                eprintln!(
+                    "{}Error: {}",
+                    prefix,
+                    result.error.as_deref().unwrap_or("unknown error")
+                );
             }
-            std::process::exit(1);
         }
     }
+
+    if any_failed {
+        std::process::exit(EXIT_ERROR);
+    }
+    if results
+        .iter()
+        .any(|r| exceeds_threshold(r, fail_on, max_diff_pixels))
+    {
+        std::process::exit(EXIT_DIFF_EXCEEDS_THRESHOLD);
+    }
 }